@@ -0,0 +1,59 @@
+use comfy_table::Color;
+use serde::{Deserialize, Serialize};
+
+/// Severity of a [`Diagnostic`], mapped to a render color at print time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    /// A purely informational note, e.g. a log emitted during the test.
+    Info,
+    /// The test passed but something about it deserves attention, e.g. it
+    /// exceeded an expected gas bound.
+    Warning,
+    /// The test failed outright, e.g. it reverted.
+    Error,
+}
+
+impl Severity {
+    /// The color this severity should be rendered in.
+    pub fn color(&self) -> Color {
+        match self {
+            Severity::Info => Color::Blue,
+            Severity::Warning => Color::Yellow,
+            Severity::Error => Color::Red,
+        }
+    }
+}
+
+/// A single structured diagnostic attached to a test result, e.g. a revert
+/// reason or a gas bound that was exceeded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// A short, stable code identifying the kind of diagnostic, e.g. `"revert"` or `"gas-bound"`.
+    pub code: String,
+    pub message: String,
+    /// The program counter the diagnostic originated at, if known.
+    pub pc: Option<usize>,
+}
+
+impl Diagnostic {
+    pub fn new(
+        severity: Severity,
+        code: impl Into<String>,
+        message: impl Into<String>,
+        pc: Option<usize>,
+    ) -> Self {
+        Self { severity, code: code.into(), message: message.into(), pc }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severity_orders_info_below_warning_below_error() {
+        assert!(Severity::Info < Severity::Warning);
+        assert!(Severity::Warning < Severity::Error);
+    }
+}
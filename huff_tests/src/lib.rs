@@ -0,0 +1,13 @@
+pub mod diagnostic;
+pub mod report;
+pub mod result;
+pub mod snapshot;
+
+pub mod prelude {
+    pub use crate::{
+        diagnostic::{Diagnostic, Severity},
+        report::{print_test_report, print_test_report_to, ReportSummary},
+        result::{ReportKind, TestResult, TestStatus},
+        snapshot::Snapshot,
+    };
+}
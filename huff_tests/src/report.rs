@@ -1,4 +1,8 @@
-use crate::prelude::{ReportKind, TestResult, TestStatus};
+use crate::{
+    diagnostic::{Diagnostic, Severity},
+    prelude::{ReportKind, TestResult, TestStatus},
+    snapshot::Snapshot,
+};
 use comfy_table::{
     modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Attribute, Cell, Color, ContentArrangement,
     Row, Table,
@@ -8,11 +12,161 @@ use ethers_core::{
     utils::{hex, parse_bytes32_string},
 };
 use huff_utils::prelude::format_even_bytes;
-use std::time::Instant;
+use std::{
+    io::{self, Write},
+    time::{Duration, Instant},
+};
 use yansi::Paint;
 
-/// Print a report of the test results, formatted according to the `report_kind` parameter.
-pub fn print_test_report(results: Vec<TestResult>, report_kind: ReportKind, start: Instant) {
+/// Decode a revert reason out of `return_data`, if it is ABI-encoded as the
+/// standard `Error(string)` selector (`08c379a0`).
+fn decode_revert_reason(return_data: &str) -> Option<String> {
+    if !return_data.starts_with("08c379a0") {
+        return None
+    }
+    let error_string = hex::decode(return_data.as_bytes()).ok()?;
+    let decoded = decode(&[ParamType::String], &error_string[4..]).ok()?;
+    decoded.into_iter().next()?.into_string()
+}
+
+/// Escape the characters that are not valid inside an XML attribute value.
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render the gas delta between `gas` and the gas recorded for `name` in
+/// `snapshot`, colored green for a saving and red for a regression.
+fn gas_delta_string(snapshot: &Snapshot, name: &str, gas: u64) -> String {
+    match snapshot.gas_for(name) {
+        Some(previous) => {
+            let delta = gas as i64 - previous as i64;
+            match delta.cmp(&0) {
+                std::cmp::Ordering::Less => format!("{}", Paint::green(format!("{delta:+}"))),
+                std::cmp::Ordering::Greater => format!("{}", Paint::red(format!("{delta:+}"))),
+                std::cmp::Ordering::Equal => format!("{delta:+}"),
+            }
+        }
+        None => String::from("new"),
+    }
+}
+
+/// The same delta as [`gas_delta_string`], rendered as a [`Cell`] for the
+/// `Table` report.
+fn gas_delta_cell(snapshot: &Snapshot, name: &str, gas: u64) -> Cell {
+    match snapshot.gas_for(name) {
+        Some(previous) => {
+            let delta = gas as i64 - previous as i64;
+            let cell = Cell::new(format!("{delta:+}"));
+            match delta.cmp(&0) {
+                std::cmp::Ordering::Less => cell.fg(Color::Green),
+                std::cmp::Ordering::Greater => cell.fg(Color::Red),
+                std::cmp::Ordering::Equal => cell,
+            }
+        }
+        None => Cell::new("new"),
+    }
+}
+
+/// Paint `text` in the color associated with `severity`.
+fn paint_severity(severity: Severity, text: &str) -> String {
+    match severity {
+        Severity::Error => format!("{}", Paint::red(text)),
+        Severity::Warning => format!("{}", Paint::yellow(text)),
+        Severity::Info => format!("{}", Paint::blue(text)),
+    }
+}
+
+/// A result's diagnostics, plus any diagnostics synthesized from other
+/// fields: a revert diagnostic decoded from its return data when it failed,
+/// and a gas-bound warning when it regressed beyond `gas_threshold` against
+/// `snapshot`.
+fn effective_diagnostics(
+    result: &TestResult,
+    snapshot: Option<&Snapshot>,
+    gas_threshold: Option<u64>,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = result.diagnostics.clone();
+
+    if result.status == TestStatus::Revert && !diagnostics.iter().any(|d| d.code == "revert") {
+        let message = result
+            .return_data
+            .as_deref()
+            .and_then(decode_revert_reason)
+            .unwrap_or_else(|| String::from("test failed"));
+        diagnostics.push(Diagnostic::new(Severity::Error, "revert", message, None));
+    }
+
+    if let (Some(snapshot), Some(threshold)) = (snapshot, gas_threshold) {
+        if snapshot.regressed_beyond(&result.name, result.gas, threshold)
+            && !diagnostics.iter().any(|d| d.code == "gas-bound")
+        {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                "gas-bound",
+                format!("gas increased by more than {threshold} since the last snapshot"),
+                None,
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Summarize a result's diagnostics into a single cell for the `Table`
+/// report, colored by the most severe diagnostic present.
+fn diagnostics_cell(diagnostics: &[Diagnostic]) -> Cell {
+    match diagnostics.iter().max_by_key(|d| d.severity) {
+        Some(worst) => {
+            let summary = diagnostics.iter().map(|d| d.message.as_str()).collect::<Vec<_>>().join("; ");
+            Cell::new(summary).fg(worst.severity.color())
+        }
+        None => Cell::new("-"),
+    }
+}
+
+/// Aggregate pass/fail counts and elapsed time for a completed test report,
+/// returned so callers can set a process exit code without re-deriving them.
+#[derive(Debug, Clone, Copy)]
+pub struct ReportSummary {
+    pub n_passed: usize,
+    pub n_failed: usize,
+    /// How many tests regressed gas beyond `gas_threshold`, if one was given.
+    pub n_gas_regressed: usize,
+    pub elapsed: Duration,
+}
+
+/// Print a report of the test results to stdout, formatted according to the
+/// `report_kind` parameter. See [`print_test_report_to`] for a version that
+/// writes to an arbitrary writer.
+pub fn print_test_report(
+    results: Vec<TestResult>,
+    report_kind: ReportKind,
+    start: Instant,
+    snapshot: Option<&Snapshot>,
+    gas_threshold: Option<u64>,
+) -> io::Result<ReportSummary> {
+    print_test_report_to(&mut io::stdout(), results, report_kind, start, snapshot, gas_threshold)
+}
+
+/// Write a report of the test results to `w`, formatted according to the
+/// `report_kind` parameter, and return the aggregate pass/fail counts.
+///
+/// When `snapshot` is provided, a gas delta is rendered alongside each
+/// result's gas usage in the `Table` and `List` reports, diffed against the
+/// gas recorded for that test name in the snapshot. When `gas_threshold` is
+/// also given, `ReportSummary::n_gas_regressed` counts how many tests
+/// regressed by more than that many gas, so a caller can fail CI on it.
+pub fn print_test_report_to(
+    w: &mut impl Write,
+    results: Vec<TestResult>,
+    report_kind: ReportKind,
+    start: Instant,
+    snapshot: Option<&Snapshot>,
+    gas_threshold: Option<u64>,
+) -> io::Result<ReportSummary> {
     // Gather how many of our tests passed *before* generating our report,
     // as we pass ownership of `results` to both the `ReportKind::Table`
     // and `ReportKind::List` arms.
@@ -23,90 +177,133 @@ pub fn print_test_report(results: Vec<TestResult>, report_kind: ReportKind, star
         })
         .count();
     let n_results = results.len();
+    let n_gas_regressed = match (snapshot, gas_threshold) {
+        (Some(snapshot), Some(threshold)) => results
+            .iter()
+            .filter(|r| snapshot.regressed_beyond(&r.name, r.gas, threshold))
+            .count(),
+        _ => 0,
+    };
 
-    // Generate and print a report of the test results, formatted based on
+    // Generate and write a report of the test results, formatted based on
     // the `report_kind` input.
     match report_kind {
         ReportKind::Table => {
             let mut table = Table::new();
             table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
-            table.set_header(Row::from(vec![
+            let mut header = vec![
                 Cell::new("Name").fg(Color::Magenta),
                 Cell::new("Return Data").fg(Color::Yellow),
                 Cell::new("Gas").fg(Color::Cyan),
-                Cell::new("Status").fg(Color::Blue),
-            ]));
+            ];
+            if snapshot.is_some() {
+                header.push(Cell::new("Δ Gas").fg(Color::Cyan));
+            }
+            header.push(Cell::new("Diagnostics").fg(Color::Yellow));
+            header.push(Cell::new("Status").fg(Color::Blue));
+            table.set_header(Row::from(header));
             table.set_content_arrangement(ContentArrangement::DynamicFullWidth);
             table.set_width(120);
 
             for result in results {
-                table.add_row(Row::from(vec![
-                    Cell::new(result.name).add_attribute(Attribute::Bold).fg(Color::Cyan),
+                let diagnostics = effective_diagnostics(&result, snapshot, gas_threshold);
+                let mut row = vec![
+                    Cell::new(&result.name).add_attribute(Attribute::Bold).fg(Color::Cyan),
                     Cell::new(result.return_data.unwrap_or_else(|| String::from("None"))),
                     Cell::new(result.gas.to_string()),
-                    Cell::from(result.status),
-                ]));
+                ];
+                if let Some(snapshot) = snapshot {
+                    row.push(gas_delta_cell(snapshot, &result.name, result.gas));
+                }
+                row.push(diagnostics_cell(&diagnostics));
+                row.push(Cell::from(result.status));
+                table.add_row(Row::from(row));
             }
 
-            println!("{table}");
+            writeln!(w, "{table}")?;
         }
         ReportKind::List => {
             for result in results {
-                println!(
+                let diagnostics = effective_diagnostics(&result, snapshot, gas_threshold);
+
+                writeln!(
+                    w,
                     "[{0}] {1: <15} - {2} {3: <20}",
                     String::from(result.status),
                     result.name,
                     Paint::yellow("Gas used:"),
                     result.gas
-                );
+                )?;
+
+                if let Some(snapshot) = snapshot {
+                    writeln!(
+                        w,
+                        "    {} {}",
+                        Paint::yellow("Δ Gas:"),
+                        gas_delta_string(snapshot, &result.name, result.gas)
+                    )?;
+                }
 
                 let num_logs = result.logs.len().saturating_sub(1);
 
                 if let Some(return_data) = result.return_data {
-                    println!("├─ {}", Paint::cyan("RETURN DATA"));
-                    println!("{} {return_data}", if num_logs == 0 { "╰─" } else { "├─" });
+                    writeln!(w, "├─ {}", Paint::cyan("RETURN DATA"))?;
+                    writeln!(w, "{} {return_data}", if num_logs == 0 { "╰─" } else { "├─" })?;
                 }
 
                 if num_logs > 0 {
-                    println!("├─ {}", Paint::cyan("LOGS"));
-                    result.logs.iter().enumerate().for_each(|(i, (pc, log))| {
+                    writeln!(w, "├─ {}", Paint::cyan("LOGS"))?;
+                    for (i, (pc, log)) in result.logs.iter().enumerate() {
                         let log = format!(
                             "[{}: {}]: 0x{}",
                             Paint::magenta("PC"),
                             Paint::yellow(pc),
                             log,
                         );
-                        println!("{} {log}", if i == num_logs { "╰─" } else { "├─" });
+                        writeln!(w, "{} {log}", if i == num_logs { "╰─" } else { "├─" })?;
                         // ├╌
-                    });
+                    }
+                }
+
+                if !diagnostics.is_empty() {
+                    writeln!(w, "├─ {}", Paint::cyan("DIAGNOSTICS"))?;
+                    let grouped: Vec<&Diagnostic> = [Severity::Error, Severity::Warning, Severity::Info]
+                        .into_iter()
+                        .flat_map(|severity| diagnostics.iter().filter(move |d| d.severity == severity))
+                        .collect();
+                    let n_diagnostics = grouped.len();
+                    for (i, diagnostic) in grouped.iter().enumerate() {
+                        let prefix = if i == n_diagnostics - 1 { "╰─" } else { "├─" };
+                        let message = paint_severity(
+                            diagnostic.severity,
+                            &format!("[{}] {}", diagnostic.code, diagnostic.message),
+                        );
+                        writeln!(w, "{prefix} {message}")?;
+                    }
                 }
             }
         }
         ReportKind::HuffTest => {
             for result in results {
-                println!(
+                writeln!(
+                    w,
                     "[{}] {: <15} - {} {: <20}",
                     String::from(result.status),
                     result.name,
                     Paint::yellow("Gas used:"),
                     result.gas
-                );
+                )?;
 
                 let num_logs = result.logs.len().saturating_sub(1);
 
                 if let Some(return_data) = &result.return_data {
-                    let message = if return_data.starts_with("08c379a0") {
-                        let error_string = hex::decode(return_data.as_bytes()).unwrap();
-                        let decoded = decode(&[ParamType::String], &error_string[4..]).unwrap();
-                        decoded[0].clone().into_string().unwrap()
-                    } else {
-                        String::from("test failed")
-                    };
-                    println!("{} {}", if num_logs == 0 { "╰─" } else { "├─" }, Paint::red(message));
+                    let message =
+                        decode_revert_reason(return_data).unwrap_or_else(|| String::from("test failed"));
+                    writeln!(w, "{} {}", if num_logs == 0 { "╰─" } else { "├─" }, Paint::red(message))?;
                 }
 
                 if num_logs > 0 {
-                    result.logs.iter().enumerate().for_each(|(i, (_, log))| {
+                    for (i, (_, log)) in result.logs.iter().enumerate() {
                         let log_string = if log.starts_with("32d5ab96") {
                             let message = hex::decode(log[8..].as_bytes()).unwrap();
                             let mut bytes = [0u8; 32];
@@ -129,25 +326,185 @@ pub fn print_test_report(results: Vec<TestResult>, report_kind: ReportKind, star
                             format!("{} 0x{}", prefix, format_even_bytes(bytes))
                         };
 
-                        println!("{}", log_string);
-                    });
+                        writeln!(w, "{}", log_string)?;
+                    }
+                }
+            }
+        }
+        ReportKind::JUnit => {
+            let failures = n_results - n_passed;
+            writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+            writeln!(
+                w,
+                r#"<testsuite tests="{}" failures="{}" time="{:.4}">"#,
+                n_results,
+                failures,
+                start.elapsed().as_secs_f64()
+            )?;
+
+            for result in results {
+                // See ReportKind::JUnit: `time` is intentionally omitted here.
+                writeln!(w, r#"  <testcase name="{}">"#, xml_escape(&result.name))?;
+
+                if std::mem::discriminant(&result.status) != std::mem::discriminant(&TestStatus::Success)
+                {
+                    let message = result
+                        .return_data
+                        .as_deref()
+                        .and_then(decode_revert_reason)
+                        .unwrap_or_else(|| String::from("test failed"));
+                    writeln!(w, r#"    <failure message="{}" />"#, xml_escape(&message))?;
                 }
+
+                writeln!(w, "    <properties>")?;
+                writeln!(w, r#"      <property name="gas" value="{}" />"#, result.gas)?;
+                if let Some(return_data) = &result.return_data {
+                    writeln!(
+                        w,
+                        r#"      <property name="return_data" value="{}" />"#,
+                        xml_escape(return_data)
+                    )?;
+                }
+                writeln!(w, "    </properties>")?;
+
+                writeln!(w, "  </testcase>")?;
             }
+
+            writeln!(w, "</testsuite>")?;
+            return Ok(ReportSummary {
+                n_passed,
+                n_failed: n_results - n_passed,
+                n_gas_regressed,
+                elapsed: start.elapsed(),
+            })
         }
         ReportKind::JSON => {
-            if let Ok(o) = serde_json::to_string_pretty(&results) {
-                println!("{o}");
-            } else {
-                eprintln!("Error serializing test results into JSON.");
+            match serde_json::to_string_pretty(&results) {
+                Ok(o) => writeln!(w, "{o}")?,
+                Err(_) => eprintln!("Error serializing test results into JSON."),
             }
-            return
+            return Ok(ReportSummary {
+                n_passed,
+                n_failed: n_results - n_passed,
+                n_gas_regressed,
+                elapsed: start.elapsed(),
+            })
         }
     }
-    println!(
+    writeln!(
+        w,
         "➜ {} tests passed, {} tests failed ({}%). ⏱ : {}",
         Paint::green(n_passed),
         Paint::red(n_results - n_passed),
         Paint::yellow(n_passed * 100 / n_results),
         Paint::magenta(format!("{:.4?}", start.elapsed()))
-    );
+    )?;
+
+    Ok(ReportSummary {
+        n_passed,
+        n_failed: n_results - n_passed,
+        n_gas_regressed,
+        elapsed: start.elapsed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::TestStatus;
+
+    fn result(name: &str, gas: u64, status: TestStatus, return_data: Option<&str>) -> TestResult {
+        TestResult {
+            name: name.to_string(),
+            return_data: return_data.map(String::from),
+            gas,
+            status,
+            logs: vec![],
+            diagnostics: vec![],
+        }
+    }
+
+    #[test]
+    fn gas_delta_is_signed_and_colored_by_direction() {
+        let path = std::env::temp_dir().join("huff-tests-report-gas-delta");
+        Snapshot::write(&[result("test_a", 100, TestStatus::Success, None)], &path).unwrap();
+        let snapshot = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(gas_delta_string(&snapshot, "test_a", 100), "+0");
+        assert!(gas_delta_string(&snapshot, "test_a", 150).contains("+50"));
+        assert!(gas_delta_string(&snapshot, "test_a", 50).contains("-50"));
+        assert_eq!(gas_delta_string(&snapshot, "unknown", 50), "new");
+    }
+
+    #[test]
+    fn escapes_xml_special_characters() {
+        assert_eq!(xml_escape(r#"<a & "b">"#), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+
+    #[test]
+    fn decodes_a_revert_reason() {
+        let encoded = "08c379a00000000000000000000000000000000000000000000000000000000000000020\
+0000000000000000000000000000000000000000000000000000000000000004\
+6661696c00000000000000000000000000000000000000000000000000000000";
+        assert_eq!(decode_revert_reason(encoded), Some(String::from("fail")));
+        assert_eq!(decode_revert_reason("deadbeef"), None);
+    }
+
+    #[test]
+    fn junit_report_omits_the_unavailable_per_testcase_time() {
+        let results = vec![result("test_pass", 100, TestStatus::Success, None)];
+        let mut buf = Vec::new();
+        print_test_report_to(&mut buf, results, ReportKind::JUnit, Instant::now(), None, None)
+            .unwrap();
+        let xml = String::from_utf8(buf).unwrap();
+
+        assert!(xml.contains(r#"<testcase name="test_pass">"#));
+        assert!(!xml.contains(r#"<testcase name="test_pass" time="#));
+    }
+
+    #[test]
+    fn gas_regression_beyond_threshold_becomes_a_warning_diagnostic() {
+        let path = std::env::temp_dir().join("huff-tests-report-gas-bound");
+        Snapshot::write(&[result("test_a", 100, TestStatus::Success, None)], &path).unwrap();
+        let snapshot = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let regressed = result("test_a", 200, TestStatus::Success, None);
+        let diagnostics = effective_diagnostics(&regressed, Some(&snapshot), Some(10));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].code, "gas-bound");
+
+        let within_bound = result("test_a", 105, TestStatus::Success, None);
+        assert!(effective_diagnostics(&within_bound, Some(&snapshot), Some(10)).is_empty());
+    }
+
+    #[test]
+    fn table_and_list_reports_render_without_moving_return_data_twice() {
+        let results = vec![result("test_pass", 100, TestStatus::Success, Some("cafe"))];
+        let mut buf = Vec::new();
+        print_test_report_to(&mut buf, results.clone(), ReportKind::Table, Instant::now(), None, None)
+            .unwrap();
+        let mut buf = Vec::new();
+        print_test_report_to(&mut buf, results, ReportKind::List, Instant::now(), None, None).unwrap();
+    }
+
+    #[test]
+    fn writes_pure_json_and_returns_the_summary() {
+        let results = vec![
+            result("test_pass", 100, TestStatus::Success, None),
+            result("test_fail", 200, TestStatus::Revert, None),
+        ];
+        let mut buf = Vec::new();
+        let summary =
+            print_test_report_to(&mut buf, results, ReportKind::JSON, Instant::now(), None, None)
+                .unwrap();
+
+        assert_eq!(summary.n_passed, 1);
+        assert_eq!(summary.n_failed, 1);
+        assert_eq!(summary.n_gas_regressed, 0);
+        let parsed: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert!(parsed.is_array());
+    }
 }
@@ -0,0 +1,56 @@
+use crate::diagnostic::Diagnostic;
+use comfy_table::{Cell, Color};
+use serde::Serialize;
+
+/// The pass/fail outcome of a single Huff test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TestStatus {
+    Success,
+    Revert,
+}
+
+impl From<TestStatus> for String {
+    fn from(status: TestStatus) -> Self {
+        match status {
+            TestStatus::Success => String::from("PASS"),
+            TestStatus::Revert => String::from("FAIL"),
+        }
+    }
+}
+
+impl From<TestStatus> for Cell {
+    fn from(status: TestStatus) -> Self {
+        match status {
+            TestStatus::Success => Cell::new("PASS").fg(Color::Green),
+            TestStatus::Revert => Cell::new("FAIL").fg(Color::Red),
+        }
+    }
+}
+
+/// The format `print_test_report` renders a set of `TestResult`s in.
+#[derive(Debug, Clone, Copy)]
+pub enum ReportKind {
+    Table,
+    List,
+    HuffTest,
+    /// JUnit XML, for CI test-report viewers. Deviates from the usual JUnit
+    /// schema in one respect: `<testcase>` omits `time`, since `TestResult`
+    /// doesn't track a per-test duration and faking it from the shared suite
+    /// clock would misreport every test as having taken the whole suite's
+    /// runtime.
+    JUnit,
+    JSON,
+}
+
+/// The outcome of running a single Huff test.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestResult {
+    pub name: String,
+    pub return_data: Option<String>,
+    pub gas: u64,
+    pub status: TestStatus,
+    pub logs: Vec<(usize, String)>,
+    /// Structured diagnostics accumulated while running this test, e.g. a
+    /// decoded revert reason or a gas bound that was exceeded.
+    pub diagnostics: Vec<Diagnostic>,
+}
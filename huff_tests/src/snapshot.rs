@@ -0,0 +1,83 @@
+use crate::prelude::TestResult;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+/// A gas snapshot: the gas cost recorded for each test the last time the
+/// snapshot file was written, keyed by test name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot(BTreeMap<String, u64>);
+
+impl Snapshot {
+    /// Load a snapshot from a previously written file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Build a snapshot from a completed test run and write it out to `path`,
+    /// overwriting any existing snapshot.
+    pub fn write(results: &[TestResult], path: impl AsRef<Path>) -> io::Result<()> {
+        let snapshot =
+            Self(results.iter().map(|result| (result.name.clone(), result.gas)).collect());
+        let contents = serde_json::to_string_pretty(&snapshot)?;
+        fs::write(path, contents)
+    }
+
+    /// The gas recorded for `name` in this snapshot, if it was present in the
+    /// last run.
+    pub fn gas_for(&self, name: &str) -> Option<u64> {
+        self.0.get(name).copied()
+    }
+
+    /// Whether `gas` regressed against the snapshot for `name` by more than
+    /// `threshold` gas. Used to count gas regressions in a report's summary
+    /// and to flag them with a `gas-bound` warning diagnostic.
+    pub fn regressed_beyond(&self, name: &str, gas: u64, threshold: u64) -> bool {
+        match self.gas_for(name) {
+            Some(previous) if gas > previous => gas - previous > threshold,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::TestStatus;
+
+    fn result(name: &str, gas: u64) -> TestResult {
+        TestResult {
+            name: name.to_string(),
+            return_data: None,
+            gas,
+            status: TestStatus::Success,
+            logs: vec![],
+            diagnostics: vec![],
+        }
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let dir = std::env::temp_dir().join("huff-tests-snapshot-round-trip");
+        Snapshot::write(&[result("test_a", 100), result("test_b", 200)], &dir).unwrap();
+        let snapshot = Snapshot::load(&dir).unwrap();
+        std::fs::remove_file(&dir).unwrap();
+
+        assert_eq!(snapshot.gas_for("test_a"), Some(100));
+        assert_eq!(snapshot.gas_for("test_b"), Some(200));
+        assert_eq!(snapshot.gas_for("test_c"), None);
+    }
+
+    #[test]
+    fn regresses_only_beyond_the_threshold() {
+        let dir = std::env::temp_dir().join("huff-tests-snapshot-regression");
+        Snapshot::write(&[result("test_a", 100)], &dir).unwrap();
+        let snapshot = Snapshot::load(&dir).unwrap();
+        std::fs::remove_file(&dir).unwrap();
+
+        assert!(!snapshot.regressed_beyond("test_a", 105, 10));
+        assert!(snapshot.regressed_beyond("test_a", 115, 10));
+        assert!(!snapshot.regressed_beyond("test_a", 90, 10));
+        assert!(!snapshot.regressed_beyond("unknown", 1_000, 10));
+    }
+}